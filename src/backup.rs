@@ -0,0 +1,118 @@
+use anyhow::{bail, Context};
+use p256::ecdsa::{
+    signature::{Signer, Verifier},
+    Signature, SigningKey, VerifyingKey,
+};
+use p256::pkcs8::{DecodePublicKey, EncodePublicKey};
+use rand_core::OsRng;
+
+// Magic bytes identifying an RPWM signed export file.
+const EXPORT_MAGIC: &[u8; 4] = b"RBAK";
+// Current export container format version.
+const EXPORT_VERSION: u16 = 1;
+
+/// A signed, verifiable vault export: the raw encrypted vault blob (the
+/// same bytes the `StorageBackend` stores) alongside a detached P-256
+/// ECDSA signature and the DER-encoded public key needed to check it.
+pub struct BackupContainer {
+    pub public_key_der: Vec<u8>,
+    pub signature_der: Vec<u8>,
+    pub vault_blob: Vec<u8>,
+}
+
+impl BackupContainer {
+    /// Signs `vault_blob` with `signing_key` and bundles it with the
+    /// corresponding public key into an exportable container.
+    pub fn sign(signing_key: &SigningKey, vault_blob: Vec<u8>) -> anyhow::Result<Self> {
+        let signature: Signature = signing_key.sign(&vault_blob);
+        let public_key_der = signing_key
+            .verifying_key()
+            .to_public_key_der()
+            .context("Failed to DER-encode the backup signing public key")?
+            .as_bytes()
+            .to_vec();
+
+        Ok(BackupContainer {
+            public_key_der,
+            signature_der: signature.to_der().as_bytes().to_vec(),
+            vault_blob,
+        })
+    }
+
+    /// Verifies the embedded signature over the vault blob using the
+    /// embedded public key. Fails closed: any parse or verification error
+    /// refuses the import.
+    pub fn verify(&self) -> anyhow::Result<()> {
+        let public_key = VerifyingKey::from_public_key_der(&self.public_key_der)
+            .context("Backup contains an invalid public key")?;
+        let signature = Signature::from_der(&self.signature_der)
+            .context("Backup contains an invalid signature")?;
+        public_key
+            .verify(&self.vault_blob, &signature)
+            .context("Backup signature verification failed (tampered or corrupted file)")
+    }
+
+    pub fn write(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(EXPORT_MAGIC);
+        out.extend_from_slice(&EXPORT_VERSION.to_le_bytes());
+        out.extend_from_slice(&(self.public_key_der.len() as u16).to_le_bytes());
+        out.extend_from_slice(&self.public_key_der);
+        out.extend_from_slice(&(self.signature_der.len() as u16).to_le_bytes());
+        out.extend_from_slice(&self.signature_der);
+        out.extend_from_slice(&self.vault_blob);
+        out
+    }
+
+    /// Parses the container's magic, version and length-prefixed fields
+    /// before any decryption is attempted, so a foreign or corrupted file
+    /// is rejected with a clear error instead of a generic decrypt failure.
+    pub fn read(data: &[u8]) -> anyhow::Result<Self> {
+        if data.len() < EXPORT_MAGIC.len() + 2 {
+            bail!("Backup file is too short to contain a header");
+        }
+        let (magic, rest) = data.split_at(EXPORT_MAGIC.len());
+        if magic != EXPORT_MAGIC {
+            bail!("Not a recognized vault backup file (bad magic)");
+        }
+        let (version_bytes, rest) = rest.split_at(2);
+        let version = u16::from_le_bytes(version_bytes.try_into().unwrap());
+        if version != EXPORT_VERSION {
+            bail!("Unsupported backup format version: {version}");
+        }
+
+        let (public_key_der, rest) = read_length_prefixed(rest)?;
+        let (signature_der, rest) = read_length_prefixed(rest)?;
+
+        Ok(BackupContainer {
+            public_key_der: public_key_der.to_vec(),
+            signature_der: signature_der.to_vec(),
+            vault_blob: rest.to_vec(),
+        })
+    }
+}
+
+fn read_length_prefixed(data: &[u8]) -> anyhow::Result<(&[u8], &[u8])> {
+    if data.len() < 2 {
+        bail!("Backup file is truncated");
+    }
+    let (len_bytes, rest) = data.split_at(2);
+    let len = u16::from_le_bytes(len_bytes.try_into().unwrap()) as usize;
+    if rest.len() < len {
+        bail!("Backup file is truncated");
+    }
+    Ok(rest.split_at(len))
+}
+
+/// Generates a fresh P-256 ECDSA signing key for a vault's backup identity.
+pub fn generate_signing_key() -> SigningKey {
+    SigningKey::random(&mut OsRng)
+}
+
+pub fn signing_key_to_bytes(key: &SigningKey) -> Vec<u8> {
+    key.to_bytes().to_vec()
+}
+
+pub fn signing_key_from_bytes(bytes: &[u8]) -> anyhow::Result<SigningKey> {
+    SigningKey::from_slice(bytes).context("Stored backup signing key is invalid")
+}
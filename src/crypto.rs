@@ -0,0 +1,378 @@
+use aes_gcm::{
+    aead::{generic_array::GenericArray, typenum::U12, Aead as _, KeyInit as _, OsRng},
+    Aes256Gcm,
+};
+use anyhow::{bail, Context};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::ChaCha20Poly1305;
+use clap::ValueEnum;
+use rand::RngCore;
+use secrecy::{ExposeSecret, Secret, SecretString};
+use zeroize::Zeroize;
+
+type Nonce = GenericArray<u8, U12>;
+
+pub const AES_KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const SALT_LEN: usize = 16;
+
+/// A 256-bit AES/ChaCha20 key that zeroizes its backing memory on drop and
+/// never prints its contents via `Debug`.
+pub type AesKey = Secret<[u8; AES_KEY_LEN]>;
+
+// Magic bytes identifying an RPWM store file.
+const MAGIC: &[u8; 4] = b"RPWM";
+// Current on-disk store format version.
+const STORE_VERSION: u16 = 3;
+
+/// Which AEAD cipher protects this store. Recorded in the header so it can
+/// be chosen at open time instead of hardcoded, and so different vaults can
+/// pick the cipher that suits their hardware.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum CryptoAlgorithm {
+    #[value(name = "aes256-gcm")]
+    Aes256Gcm,
+    #[value(name = "chacha20-poly1305")]
+    ChaCha20Poly1305,
+}
+
+impl CryptoAlgorithm {
+    fn to_byte(self) -> u8 {
+        match self {
+            CryptoAlgorithm::Aes256Gcm => 0,
+            CryptoAlgorithm::ChaCha20Poly1305 => 1,
+        }
+    }
+
+    fn from_byte(byte: u8) -> anyhow::Result<Self> {
+        match byte {
+            0 => Ok(CryptoAlgorithm::Aes256Gcm),
+            1 => Ok(CryptoAlgorithm::ChaCha20Poly1305),
+            other => bail!("Unknown cipher id: {other}"),
+        }
+    }
+}
+
+// Argon2 parameters used to derive the key-encryption-key (KEK) from the
+// master password. Persisted in the header so every open re-derives the
+// exact same key, and so the parameters can be upgraded later via
+// `STORE_VERSION`.
+struct KdfParams {
+    algorithm: Algorithm,
+    version: Version,
+    t_cost: u32,
+    m_cost: u32,
+    p_cost: u32,
+    salt: [u8; SALT_LEN],
+}
+
+impl KdfParams {
+    fn generate() -> Self {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let params = Params::default();
+        KdfParams {
+            algorithm: Algorithm::default(),
+            version: Version::default(),
+            t_cost: params.t_cost(),
+            m_cost: params.m_cost(),
+            p_cost: params.p_cost(),
+            salt,
+        }
+    }
+
+    fn derive_key(&self, master_password: &SecretString) -> anyhow::Result<AesKey> {
+        let params = Params::new(self.m_cost, self.t_cost, self.p_cost, Some(AES_KEY_LEN))
+            .context("Invalid Argon2 parameters")?;
+        let argon2 = Argon2::new(self.algorithm, self.version, params);
+        let mut key = [0u8; AES_KEY_LEN];
+        argon2
+            .hash_password_into(master_password.expose_secret().as_bytes(), &self.salt, &mut key)
+            .map_err(|e| anyhow::anyhow!("Key derivation failed: {e}"))?;
+        Ok(Secret::new(key))
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.push(algorithm_to_byte(self.algorithm));
+        out.extend_from_slice(&(self.version as u32).to_le_bytes());
+        out.extend_from_slice(&self.t_cost.to_le_bytes());
+        out.extend_from_slice(&self.m_cost.to_le_bytes());
+        out.extend_from_slice(&self.p_cost.to_le_bytes());
+        out.extend_from_slice(&(self.salt.len() as u16).to_le_bytes());
+        out.extend_from_slice(&self.salt);
+    }
+
+    fn read(data: &[u8]) -> anyhow::Result<(Self, usize)> {
+        if data.len() < 1 + 4 + 4 + 4 + 4 + 2 {
+            bail!("Store header is truncated");
+        }
+        let (algo_byte, rest) = data.split_at(1);
+        let algorithm = byte_to_algorithm(algo_byte[0])?;
+        let (argon_version_bytes, rest) = rest.split_at(4);
+        let argon_version = u32::from_le_bytes(argon_version_bytes.try_into().unwrap());
+        let argon_version = Version::try_from(argon_version)
+            .map_err(|_| anyhow::anyhow!("Unknown Argon2 version: {argon_version}"))?;
+        let (t_cost_bytes, rest) = rest.split_at(4);
+        let t_cost = u32::from_le_bytes(t_cost_bytes.try_into().unwrap());
+        let (m_cost_bytes, rest) = rest.split_at(4);
+        let m_cost = u32::from_le_bytes(m_cost_bytes.try_into().unwrap());
+        let (p_cost_bytes, rest) = rest.split_at(4);
+        let p_cost = u32::from_le_bytes(p_cost_bytes.try_into().unwrap());
+        let (salt_len_bytes, rest) = rest.split_at(2);
+        let salt_len = u16::from_le_bytes(salt_len_bytes.try_into().unwrap()) as usize;
+        if salt_len != SALT_LEN || rest.len() < salt_len {
+            bail!("Store header has an invalid salt length");
+        }
+        let (salt_bytes, rest) = rest.split_at(salt_len);
+        let mut salt = [0u8; SALT_LEN];
+        salt.copy_from_slice(salt_bytes);
+
+        let consumed = data.len() - rest.len();
+        Ok((
+            KdfParams {
+                algorithm,
+                version: argon_version,
+                t_cost,
+                m_cost,
+                p_cost,
+                salt,
+            },
+            consumed,
+        ))
+    }
+}
+
+fn algorithm_to_byte(algorithm: Algorithm) -> u8 {
+    match algorithm {
+        Algorithm::Argon2d => 0,
+        Algorithm::Argon2i => 1,
+        Algorithm::Argon2id => 2,
+    }
+}
+
+fn byte_to_algorithm(byte: u8) -> anyhow::Result<Algorithm> {
+    match byte {
+        0 => Ok(Algorithm::Argon2d),
+        1 => Ok(Algorithm::Argon2i),
+        2 => Ok(Algorithm::Argon2id),
+        other => bail!("Unknown Argon2 algorithm id: {other}"),
+    }
+}
+
+// The store header never exposes the master password to the data it
+// protects. A random data-encryption-key (DEK) encrypts the
+// `PasswordStore` body, and the DEK itself is sealed under a
+// key-encryption-key (KEK) derived from the master password. Rotating the
+// master password (`change-password`) then only needs to re-derive the
+// KEK and re-seal the DEK, without touching the encrypted body at all.
+pub struct StoreHeader {
+    cipher: CryptoAlgorithm,
+    kdf: KdfParams,
+    seal_nonce: [u8; NONCE_LEN],
+    sealed_dek: Vec<u8>,
+}
+
+impl StoreHeader {
+    /// Generates a fresh DEK, seals it under a KEK derived from
+    /// `master_password`, and returns the header alongside the DEK to use
+    /// for encrypting the store body. `cipher` is recorded in the header
+    /// and used for both the seal and the body.
+    pub fn seal_new(
+        master_password: &SecretString,
+        cipher: CryptoAlgorithm,
+    ) -> anyhow::Result<(Self, AesKey)> {
+        let mut dek_bytes = [0u8; AES_KEY_LEN];
+        OsRng.fill_bytes(&mut dek_bytes);
+        let dek = Secret::new(dek_bytes);
+
+        let kdf = KdfParams::generate();
+        let kek = kdf.derive_key(master_password)?;
+        let (seal_nonce, sealed_dek) = seal(cipher, &kek, &dek)?;
+
+        Ok((
+            StoreHeader {
+                cipher,
+                kdf,
+                seal_nonce,
+                sealed_dek,
+            },
+            dek,
+        ))
+    }
+
+    /// Unseals the DEK using a KEK derived from `master_password`. Fails
+    /// with an authentication error if the password is wrong.
+    pub fn unseal_dek(&self, master_password: &SecretString) -> anyhow::Result<AesKey> {
+        let kek = self.kdf.derive_key(master_password)?;
+        unseal(self.cipher, &kek, &self.seal_nonce, &self.sealed_dek)
+    }
+
+    /// Re-derives the KEK from `new_master_password` under a fresh salt and
+    /// re-seals `dek` with the same cipher, leaving the encrypted store body
+    /// untouched.
+    pub fn reseal(&self, new_master_password: &SecretString, dek: &AesKey) -> anyhow::Result<Self> {
+        let kdf = KdfParams::generate();
+        let kek = kdf.derive_key(new_master_password)?;
+        let (seal_nonce, sealed_dek) = seal(self.cipher, &kek, dek)?;
+        Ok(StoreHeader {
+            cipher: self.cipher,
+            kdf,
+            seal_nonce,
+            sealed_dek,
+        })
+    }
+
+    /// Encrypts `plaintext` under `dek` using this header's cipher.
+    pub fn encrypt_body(&self, dek: &AesKey, plaintext: &[u8]) -> anyhow::Result<Vec<u8>> {
+        encrypt(self.cipher, dek, plaintext)
+    }
+
+    /// Decrypts a nonce-prefixed ciphertext body using this header's cipher.
+    pub fn decrypt_body(&self, dek: &AesKey, body: &[u8]) -> anyhow::Result<Vec<u8>> {
+        decrypt(self.cipher, dek, body)
+    }
+
+    pub fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&STORE_VERSION.to_le_bytes());
+        out.push(self.cipher.to_byte());
+        self.kdf.write(out);
+        out.extend_from_slice(&self.seal_nonce);
+        out.extend_from_slice(&(self.sealed_dek.len() as u16).to_le_bytes());
+        out.extend_from_slice(&self.sealed_dek);
+    }
+
+    pub fn read(data: &[u8]) -> anyhow::Result<(Self, usize)> {
+        if data.len() < MAGIC.len() + 2 {
+            bail!("Store file is too short to contain a header");
+        }
+        let (magic, rest) = data.split_at(MAGIC.len());
+        if magic != MAGIC {
+            bail!("Not a recognized password store file (bad magic)");
+        }
+        let (version_bytes, rest) = rest.split_at(2);
+        let version = u16::from_le_bytes(version_bytes.try_into().unwrap());
+        if version != STORE_VERSION {
+            bail!("Unsupported store format version: {version}");
+        }
+
+        if rest.is_empty() {
+            bail!("Store header is truncated");
+        }
+        let (cipher_byte, rest) = rest.split_at(1);
+        let cipher = CryptoAlgorithm::from_byte(cipher_byte[0])?;
+
+        let (kdf, kdf_len) = KdfParams::read(rest)?;
+        let rest = &rest[kdf_len..];
+
+        if rest.len() < NONCE_LEN + 2 {
+            bail!("Store header is truncated");
+        }
+        let (seal_nonce_bytes, rest) = rest.split_at(NONCE_LEN);
+        let mut seal_nonce = [0u8; NONCE_LEN];
+        seal_nonce.copy_from_slice(seal_nonce_bytes);
+
+        let (sealed_dek_len_bytes, rest) = rest.split_at(2);
+        let sealed_dek_len = u16::from_le_bytes(sealed_dek_len_bytes.try_into().unwrap()) as usize;
+        if rest.len() < sealed_dek_len {
+            bail!("Store header has a truncated sealed key");
+        }
+        let (sealed_dek, rest) = rest.split_at(sealed_dek_len);
+
+        let consumed = data.len() - rest.len();
+        Ok((
+            StoreHeader {
+                cipher,
+                kdf,
+                seal_nonce,
+                sealed_dek: sealed_dek.to_vec(),
+            },
+            consumed,
+        ))
+    }
+}
+
+fn seal(cipher: CryptoAlgorithm, kek: &AesKey, dek: &AesKey) -> anyhow::Result<([u8; NONCE_LEN], Vec<u8>)> {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let sealed = aead_encrypt(cipher, kek, &nonce, dek.expose_secret().as_ref())
+        .context("Failed to seal data encryption key")?;
+    Ok((nonce, sealed))
+}
+
+fn unseal(
+    cipher: CryptoAlgorithm,
+    kek: &AesKey,
+    nonce: &[u8; NONCE_LEN],
+    sealed_dek: &[u8],
+) -> anyhow::Result<AesKey> {
+    let mut dek = aead_decrypt(cipher, kek, nonce, sealed_dek)
+        .context("Failed to unseal data encryption key (wrong master password?)")?;
+    let key: [u8; AES_KEY_LEN] = dek
+        .as_slice()
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Unsealed data encryption key had an unexpected length"))?;
+    dek.zeroize();
+    Ok(Secret::new(key))
+}
+
+/// Encrypts `plaintext` under `key`, returning a 12-byte nonce prefix
+/// followed by the ciphertext.
+fn encrypt(cipher: CryptoAlgorithm, key: &AesKey, plaintext: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let ciphertext =
+        aead_encrypt(cipher, key, &nonce, plaintext).context("Failed to encrypt data")?;
+    Ok([nonce.to_vec(), ciphertext].concat())
+}
+
+/// Decrypts a nonce-prefixed ciphertext body produced by [`encrypt`].
+fn decrypt(cipher: CryptoAlgorithm, key: &AesKey, body: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if body.len() < NONCE_LEN {
+        bail!("Store file is too short to contain a nonce");
+    }
+    let (nonce_bytes, ciphertext) = body.split_at(NONCE_LEN);
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce.copy_from_slice(nonce_bytes);
+    aead_decrypt(cipher, key, &nonce, ciphertext).context("Failed to decrypt data")
+}
+
+fn aead_encrypt(
+    cipher: CryptoAlgorithm,
+    key: &AesKey,
+    nonce: &[u8; NONCE_LEN],
+    plaintext: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    let nonce = Nonce::from_slice(nonce);
+    let key = key.expose_secret();
+    match cipher {
+        CryptoAlgorithm::Aes256Gcm => {
+            let cipher = Aes256Gcm::new_from_slice(key).context("Invalid key length")?;
+            cipher.encrypt(nonce, plaintext).map_err(|_| anyhow::anyhow!("Encryption failed"))
+        }
+        CryptoAlgorithm::ChaCha20Poly1305 => {
+            let cipher = ChaCha20Poly1305::new_from_slice(key).context("Invalid key length")?;
+            cipher.encrypt(nonce, plaintext).map_err(|_| anyhow::anyhow!("Encryption failed"))
+        }
+    }
+}
+
+fn aead_decrypt(
+    cipher: CryptoAlgorithm,
+    key: &AesKey,
+    nonce: &[u8; NONCE_LEN],
+    ciphertext: &[u8],
+) -> anyhow::Result<Vec<u8>> {
+    let nonce = Nonce::from_slice(nonce);
+    let key = key.expose_secret();
+    match cipher {
+        CryptoAlgorithm::Aes256Gcm => {
+            let cipher = Aes256Gcm::new_from_slice(key).context("Invalid key length")?;
+            cipher.decrypt(nonce, ciphertext).map_err(|_| anyhow::anyhow!("Decryption failed"))
+        }
+        CryptoAlgorithm::ChaCha20Poly1305 => {
+            let cipher = ChaCha20Poly1305::new_from_slice(key).context("Invalid key length")?;
+            cipher.decrypt(nonce, ciphertext).map_err(|_| anyhow::anyhow!("Decryption failed"))
+        }
+    }
+}
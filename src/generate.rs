@@ -0,0 +1,58 @@
+use anyhow::bail;
+use rand::{rngs::OsRng, RngCore};
+
+const LOWERCASE: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &[u8] = b"0123456789";
+const SYMBOLS: &[u8] = b"!@#$%^&*()-_=+[]{};:,.<>?";
+
+/// Which character classes a generated password draws from.
+pub struct GenerateOptions {
+    pub length: usize,
+    pub uppercase: bool,
+    pub no_digits: bool,
+    pub no_symbols: bool,
+}
+
+/// Generates a random password from `opts` using `OsRng`, rejection
+/// sampling each character so no letter in the alphabet is favored by
+/// modulo bias.
+pub fn generate_password(opts: &GenerateOptions) -> anyhow::Result<String> {
+    let mut alphabet = Vec::new();
+    alphabet.extend_from_slice(LOWERCASE);
+    if opts.uppercase {
+        alphabet.extend_from_slice(UPPERCASE);
+    }
+    if !opts.no_digits {
+        alphabet.extend_from_slice(DIGITS);
+    }
+    if !opts.no_symbols {
+        alphabet.extend_from_slice(SYMBOLS);
+    }
+
+    if alphabet.is_empty() {
+        bail!("No character classes selected for password generation");
+    }
+
+    let mut rng = OsRng;
+    let password = (0..opts.length)
+        .map(|_| alphabet[random_index(&mut rng, alphabet.len())] as char)
+        .collect();
+    Ok(password)
+}
+
+/// Picks a uniformly random index in `0..len` via rejection sampling, so
+/// that `len` values not evenly dividing 256 don't bias towards the low end
+/// of the alphabet.
+fn random_index(rng: &mut OsRng, len: usize) -> usize {
+    let len = len as u32;
+    let limit = 256 - (256 % len);
+    loop {
+        let mut byte = [0u8; 1];
+        rng.fill_bytes(&mut byte);
+        let candidate = byte[0] as u32;
+        if candidate < limit {
+            return (candidate % len) as usize;
+        }
+    }
+}
@@ -1,9 +1,14 @@
-use aes_gcm::{
-    aead::{Aead, KeyInit, OsRng},
-    Aes256Gcm, Nonce
-};
-use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+mod backup;
+mod crypto;
+mod generate;
+mod storage;
+
+use crypto::{AesKey, CryptoAlgorithm, StoreHeader};
+use generate::GenerateOptions;
+use storage::{BackendKind, LocalFileBackend, S3Backend, StorageBackend};
+use backup::BackupContainer;
 use clap::{Parser, Subcommand};
+use secrecy::{ExposeSecret, Secret, SecretString, SecretVec};
 use serde::{Deserialize, Serialize};
 use std::{
     fs,
@@ -11,20 +16,74 @@ use std::{
     path::PathBuf,
 };
 use anyhow::Context;
-use rand::RngCore;
+use zeroize::Zeroize;
 
 // Password entry structure
 #[derive(Serialize, Deserialize)]
 struct PasswordEntry {
     name: String,
     username: String,
-    password: String,
+    #[serde(with = "secret_string")]
+    password: SecretString,
 }
 
 // Encrypted password store
 #[derive(Serialize, Deserialize)]
 struct PasswordStore {
     entries: Vec<PasswordEntry>,
+    // P-256 ECDSA signing key used to sign `export` backups, sealed simply
+    // by virtue of living inside the encrypted store body. Generated lazily
+    // on first export so every export from this vault shares one identity.
+    #[serde(with = "secret_bytes_opt", default)]
+    signing_key: Option<SecretVec<u8>>,
+}
+
+// Serializes/deserializes a `SecretString` as a plain string, so
+// `PasswordEntry::password` never needs to be unwrapped by callers just to
+// round-trip through bincode.
+mod secret_string {
+    use secrecy::{ExposeSecret, Secret, SecretString};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(secret: &SecretString, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(secret.expose_secret())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<SecretString, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        Ok(Secret::new(value))
+    }
+}
+
+// Serializes/deserializes an `Option<SecretVec<u8>>` as a plain optional
+// byte vector.
+mod secret_bytes_opt {
+    use secrecy::{ExposeSecret, Secret, SecretVec};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(secret: &Option<SecretVec<u8>>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match secret {
+            Some(secret) => serializer.serialize_some(secret.expose_secret()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<SecretVec<u8>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value: Option<Vec<u8>> = Option::deserialize(deserializer)?;
+        Ok(value.map(Secret::new))
+    }
 }
 
 // CLI Commands
@@ -41,7 +100,45 @@ enum Commands {
     /// List all entries
     List,
     /// Initialize password store
-    Init,
+    Init {
+        /// AEAD cipher used to protect this store
+        #[arg(long, value_enum, default_value = "aes256-gcm")]
+        cipher: CryptoAlgorithm,
+    },
+    /// Change the master password without re-encrypting the store
+    ChangePassword,
+    /// Generate a strong random password
+    Generate {
+        /// Length of the generated password
+        #[arg(short, long, default_value_t = 20)]
+        length: usize,
+        /// Exclude symbol characters
+        #[arg(long)]
+        no_symbols: bool,
+        /// Exclude digit characters
+        #[arg(long)]
+        no_digits: bool,
+        /// Include uppercase letters
+        #[arg(long)]
+        uppercase: bool,
+        /// Store the generated password as a new entry under this name
+        /// instead of printing it
+        #[arg(long)]
+        name: Option<String>,
+        /// Username to store alongside `--name`
+        #[arg(long, default_value = "")]
+        username: String,
+    },
+    /// Export a signed, verifiable encrypted backup of the vault
+    Export {
+        /// Where to write the backup file
+        path: PathBuf,
+    },
+    /// Restore the vault from a signed backup, verifying its signature first
+    Import {
+        /// Backup file produced by `export`
+        path: PathBuf,
+    },
 }
 
 // CLI Arguments
@@ -50,91 +147,256 @@ enum Commands {
 struct Args {
     #[command(subcommand)]
     command: Commands,
-    /// Path to password store
+    /// Path to password store (used as the S3 object key with `--backend s3`)
     #[arg(short, long, default_value = "passwords.enc")]
     store: PathBuf,
+    /// Where the encrypted vault is read from and written to
+    #[arg(long, value_enum, default_value = "local")]
+    backend: BackendKind,
+    /// S3-compatible endpoint URL (required for `--backend s3`)
+    #[arg(long, env = "RPWM_S3_ENDPOINT")]
+    s3_endpoint: Option<String>,
+    /// Bucket name (required for `--backend s3`)
+    #[arg(long, env = "RPWM_S3_BUCKET")]
+    s3_bucket: Option<String>,
+    /// S3 region
+    #[arg(long, env = "RPWM_S3_REGION", default_value = "us-east-1")]
+    s3_region: String,
+    /// S3 access key (required for `--backend s3`)
+    #[arg(long, env = "RPWM_S3_ACCESS_KEY")]
+    s3_access_key: Option<String>,
+    /// S3 secret key (required for `--backend s3`)
+    #[arg(long, env = "RPWM_S3_SECRET_KEY")]
+    s3_secret_key: Option<String>,
+}
+
+fn build_backend(args: &Args) -> anyhow::Result<Box<dyn StorageBackend>> {
+    match args.backend {
+        BackendKind::Local => Ok(Box::new(LocalFileBackend::new(args.store.clone()))),
+        BackendKind::S3 => {
+            let endpoint = args
+                .s3_endpoint
+                .clone()
+                .context("--s3-endpoint is required for --backend s3")?;
+            let bucket = args
+                .s3_bucket
+                .clone()
+                .context("--s3-bucket is required for --backend s3")?;
+            let access_key = args
+                .s3_access_key
+                .clone()
+                .context("--s3-access-key is required for --backend s3")?;
+            let secret_key = args
+                .s3_secret_key
+                .clone()
+                .context("--s3-secret-key is required for --backend s3")?;
+            let object_key = args.store.to_string_lossy().into_owned();
+            Ok(Box::new(S3Backend::new(
+                endpoint,
+                bucket,
+                args.s3_region.clone(),
+                object_key,
+                access_key,
+                secret_key,
+            )?))
+        }
+    }
 }
 
 // Main function
 fn main() -> anyhow::Result<()> {
     let args = Args::parse();
+    let backend = build_backend(&args)?;
 
     match args.command {
-        Commands::Init => init_store(&args.store),
+        Commands::Init { cipher } => init_store(backend.as_ref(), cipher),
         Commands::Add {
             name,
             username,
             password,
-        } => add_entry(&args.store, &name, &username, &password),
-        Commands::Get { name } => get_entry(&args.store, &name),
-        Commands::List => list_entries(&args.store),
+        } => add_entry(backend.as_ref(), &name, &username, &password),
+        Commands::Get { name } => get_entry(backend.as_ref(), &name),
+        Commands::List => list_entries(backend.as_ref()),
+        Commands::ChangePassword => change_password(backend.as_ref()),
+        Commands::Generate {
+            length,
+            no_symbols,
+            no_digits,
+            uppercase,
+            name,
+            username,
+        } => generate_entry(
+            backend.as_ref(),
+            GenerateOptions {
+                length,
+                uppercase,
+                no_digits,
+                no_symbols,
+            },
+            name,
+            &username,
+        ),
+        Commands::Export { path } => export_vault(backend.as_ref(), &path),
+        Commands::Import { path } => import_vault(backend.as_ref(), &path),
+    }
+}
+
+// Generate a random password, either printing it or storing it as a new entry
+fn generate_entry(
+    backend: &dyn StorageBackend,
+    opts: GenerateOptions,
+    name: Option<String>,
+    username: &str,
+) -> anyhow::Result<()> {
+    let password = generate::generate_password(&opts)?;
+    match name {
+        Some(name) => add_entry(backend, &name, username, &password),
+        None => {
+            println!("{password}");
+            Ok(())
+        }
     }
 }
 
 // Initialize password store
-fn init_store(store_path: &PathBuf) -> anyhow::Result<()> {
+fn init_store(backend: &dyn StorageBackend, cipher: CryptoAlgorithm) -> anyhow::Result<()> {
     let master_password = get_password("Set master password: ")?;
-    let salt = argon2::password_hash::SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default();
-    let hash = argon2.hash_password(master_password.as_bytes(), &salt)?;
-    
-    let store = PasswordStore { entries: Vec::new() };
-    let encrypted_data = encrypt_store(&store, hash.hash.unwrap().as_bytes())?;
-    
-    fs::write(store_path, encrypted_data)?;
+    let (header, dek) = StoreHeader::seal_new(&master_password, cipher)?;
+
+    let store = PasswordStore {
+        entries: Vec::new(),
+        signing_key: None,
+    };
+    let mut plaintext = bincode::serialize(&store)?;
+    let encrypted_body = header.encrypt_body(&dek, &plaintext);
+    plaintext.zeroize();
+    let encrypted_body = encrypted_body?;
+
+    let mut out = Vec::new();
+    header.write(&mut out);
+    out.extend_from_slice(&encrypted_body);
+    backend.store(&out)?;
+
     println!("Password store initialized!");
     Ok(())
 }
 
 // Add new entry
-fn add_entry(store_path: &PathBuf, name: &str, username: &str, password: &str) -> anyhow::Result<()> {
-    let mut store = decrypt_store(store_path)?;
-    
+fn add_entry(backend: &dyn StorageBackend, name: &str, username: &str, password: &str) -> anyhow::Result<()> {
+    let (header, dek, mut store) = open_store(backend)?;
+
     store.entries.push(PasswordEntry {
         name: name.to_string(),
         username: username.to_string(),
-        password: password.to_string(),
+        password: Secret::new(password.to_string()),
     });
-    
-    let master_password = get_password("Master password: ")?;
-    let encrypted_data = encrypt_store(&store, master_password.as_bytes())?;
-    
-    fs::write(store_path, encrypted_data)?;
+
+    save_store(backend, &header, &dek, &store)
+}
+
+// Change the master password by re-sealing the data encryption key
+fn change_password(backend: &dyn StorageBackend) -> anyhow::Result<()> {
+    let encrypted_data = backend.load()?;
+    let (header, header_len) = StoreHeader::read(&encrypted_data)?;
+    let body = &encrypted_data[header_len..];
+
+    let current_password = get_password("Current master password: ")?;
+    let dek = header.unseal_dek(&current_password)?;
+
+    let new_password = get_password("New master password: ")?;
+    let new_header = header.reseal(&new_password, &dek)?;
+
+    let mut out = Vec::new();
+    new_header.write(&mut out);
+    out.extend_from_slice(body);
+    backend.store(&out)?;
+
+    println!("Master password changed!");
+    Ok(())
+}
+
+// Exports a signed backup of the vault. The signing key lives inside the
+// encrypted store itself (sealed simply by being part of the encrypted
+// body), generated once on first export and reused afterwards so a vault
+// keeps one stable backup identity.
+fn export_vault(backend: &dyn StorageBackend, path: &PathBuf) -> anyhow::Result<()> {
+    let (header, dek, mut store) = open_store(backend)?;
+
+    if store.signing_key.is_none() {
+        let signing_key = backup::generate_signing_key();
+        store.signing_key = Some(Secret::new(backup::signing_key_to_bytes(&signing_key)));
+        save_store(backend, &header, &dek, &store)?;
+    }
+
+    let signing_key = backup::signing_key_from_bytes(
+        store
+            .signing_key
+            .as_ref()
+            .expect("signing key was just ensured to be present")
+            .expose_secret(),
+    )?;
+
+    let vault_blob = backend.load()?;
+    let container = BackupContainer::sign(&signing_key, vault_blob)?;
+    fs::write(path, container.write())
+        .with_context(|| format!("Failed to write backup to {}", path.display()))?;
+
+    println!("Backup written to {}", path.display());
     Ok(())
 }
 
-// Encryption/Decryption functions
-fn encrypt_store(store: &PasswordStore, key: &[u8]) -> anyhow::Result<Vec<u8>> {
-    let cipher = Aes256Gcm::new_from_slice(key).context("Invalid key length")?;
-    let mut nonce = [0u8; 12];
-    rand::thread_rng().fill_bytes(&mut nonce);
-    let nonce = Nonce::from_slice(&nonce);
-    let serialized = bincode::serialize(store)?;
-    let ciphertext = cipher
-        .encrypt(nonce, serialized.as_ref())
-        .context("Failed to encrypt data")?;
-    Ok([nonce.to_vec(), ciphertext].concat())
+// Verifies a signed backup's signature before restoring it, so a tampered
+// or corrupted off-site copy is refused instead of silently loaded.
+fn import_vault(backend: &dyn StorageBackend, path: &PathBuf) -> anyhow::Result<()> {
+    let data = fs::read(path).with_context(|| format!("Failed to read backup {}", path.display()))?;
+    let container = BackupContainer::read(&data)?;
+    container.verify()?;
+
+    backend.store(&container.vault_blob)?;
+    println!("Vault restored from {}", path.display());
+    Ok(())
 }
 
-fn decrypt_store(store_path: &PathBuf) -> anyhow::Result<PasswordStore> {
+// Decrypts the store and returns its header, data encryption key and
+// contents, prompting once for the master password.
+fn open_store(backend: &dyn StorageBackend) -> anyhow::Result<(StoreHeader, AesKey, PasswordStore)> {
+    let encrypted_data = backend.load()?;
+    let (header, header_len) = StoreHeader::read(&encrypted_data)?;
+
     let master_password = get_password("Master password: ")?;
-    let encrypted_data = fs::read(store_path)?;
-    
-    let (nonce, ciphertext) = encrypted_data.split_at(12);
-    let cipher = Aes256Gcm::new_from_slice(master_password.as_bytes())
-        .context("Invalid key length")?;
-    let decrypted = cipher
-        .decrypt(Nonce::from_slice(nonce), ciphertext)
-        .context("Failed to decrypt data")?;
-    
-    Ok(bincode::deserialize(&decrypted)?)
+    let dek = header.unseal_dek(&master_password)?;
+
+    let body = &encrypted_data[header_len..];
+    let mut decrypted = header.decrypt_body(&dek, body)?;
+    let store = bincode::deserialize(&decrypted);
+    decrypted.zeroize();
+    let store = store?;
+
+    Ok((header, dek, store))
+}
+
+// Re-encrypts `store` under `dek` and writes it back behind `header`.
+fn save_store(
+    backend: &dyn StorageBackend,
+    header: &StoreHeader,
+    dek: &AesKey,
+    store: &PasswordStore,
+) -> anyhow::Result<()> {
+    let mut plaintext = bincode::serialize(store)?;
+    let encrypted_body = header.encrypt_body(dek, &plaintext);
+    plaintext.zeroize();
+    let mut out = Vec::new();
+    header.write(&mut out);
+    out.extend_from_slice(&encrypted_body?);
+    backend.store(&out)?;
+    Ok(())
 }
 
 // Helper functions
-fn get_password(prompt: &str) -> anyhow::Result<String> {
+fn get_password(prompt: &str) -> anyhow::Result<SecretString> {
     print!("{}", prompt);
     io::stdout().flush()?;
     let mut password = String::new();
     io::stdin().read_line(&mut password)?;
-    Ok(password.trim().to_string())
-}
\ No newline at end of file
+    Ok(Secret::new(password.trim().to_string()))
+}
@@ -0,0 +1,102 @@
+use anyhow::{bail, Context};
+use clap::ValueEnum;
+use s3::bucket::Bucket;
+use s3::creds::Credentials;
+use s3::region::Region;
+use std::{fs, path::PathBuf};
+
+/// A place the encrypted vault blob can be read from and written to. Only
+/// ciphertext ever crosses this boundary, so a backend never needs to know
+/// anything about the master password, the DEK, or the store contents.
+pub trait StorageBackend {
+    fn load(&self) -> anyhow::Result<Vec<u8>>;
+    fn store(&self, data: &[u8]) -> anyhow::Result<()>;
+}
+
+/// Which [`StorageBackend`] to use, selected via `--backend`.
+#[derive(Clone, ValueEnum)]
+pub enum BackendKind {
+    Local,
+    S3,
+}
+
+pub struct LocalFileBackend {
+    path: PathBuf,
+}
+
+impl LocalFileBackend {
+    pub fn new(path: PathBuf) -> Self {
+        LocalFileBackend { path }
+    }
+}
+
+impl StorageBackend for LocalFileBackend {
+    fn load(&self) -> anyhow::Result<Vec<u8>> {
+        fs::read(&self.path).with_context(|| format!("Failed to read {}", self.path.display()))
+    }
+
+    fn store(&self, data: &[u8]) -> anyhow::Result<()> {
+        fs::write(&self.path, data)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+/// Stores the encrypted vault as a single object in an S3/Garage-compatible
+/// bucket, so one vault can be synced across machines without the storage
+/// provider ever seeing plaintext.
+pub struct S3Backend {
+    bucket: Box<Bucket>,
+    object_key: String,
+}
+
+impl S3Backend {
+    pub fn new(
+        endpoint: String,
+        bucket: String,
+        region: String,
+        object_key: String,
+        access_key: String,
+        secret_key: String,
+    ) -> anyhow::Result<Self> {
+        let credentials = Credentials::new(Some(&access_key), Some(&secret_key), None, None, None)
+            .context("Failed to build S3 credentials")?;
+        let region = Region::Custom { region, endpoint };
+        let bucket = Bucket::new(&bucket, region, credentials)
+            .context("Failed to configure S3 bucket")?
+            .with_path_style();
+
+        Ok(S3Backend { bucket, object_key })
+    }
+}
+
+impl StorageBackend for S3Backend {
+    fn load(&self) -> anyhow::Result<Vec<u8>> {
+        let response = self
+            .bucket
+            .get_object_blocking(&self.object_key)
+            .context("Failed to download vault from S3")?;
+        if response.status_code() != 200 {
+            bail!(
+                "S3 returned status {} fetching {}",
+                response.status_code(),
+                self.object_key
+            );
+        }
+        Ok(response.bytes().to_vec())
+    }
+
+    fn store(&self, data: &[u8]) -> anyhow::Result<()> {
+        let response = self
+            .bucket
+            .put_object_blocking(&self.object_key, data)
+            .context("Failed to upload vault to S3")?;
+        if response.status_code() != 200 {
+            bail!(
+                "S3 returned status {} uploading {}",
+                response.status_code(),
+                self.object_key
+            );
+        }
+        Ok(())
+    }
+}